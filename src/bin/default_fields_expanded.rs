@@ -0,0 +1,306 @@
+use core::fmt::{self, Formatter};
+use std::marker::PhantomData;
+
+use serde::{
+    de::{Error, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+pub fn main() {
+    let string = r#"{ "name": "Rocko", "age": 4 }"#;
+
+    let deserialized_dog: Dog = serde_json::from_str(string).unwrap();
+
+    println!(
+        "Deserialized dog nickname = {:?}, temperament = {}",
+        deserialized_dog.nickname, deserialized_dog.temperament
+    );
+}
+
+fn default_temperament() -> String {
+    "Friendly".to_owned()
+}
+
+#[derive(Deserialize)]
+enum Breed {
+    #[allow(dead_code)]
+    Husky,
+    #[allow(dead_code)]
+    Teckel,
+}
+
+struct Dog {
+    #[allow(dead_code)]
+    name: String,
+    #[allow(dead_code)]
+    age: u8,
+    #[allow(dead_code)]
+    breed: Option<Breed>,
+    nickname: String,
+    temperament: String,
+}
+
+/// Answers the two questions derive asks when a field is absent from the
+/// input: "is this field `Option<T>`?" (then `None` via `visit_none`) and
+/// anything else is a hard error, same as a field that was never declared
+/// `#[serde(default)]`.
+struct MissingFieldDeserializer<E> {
+    name: &'static str,
+    marker: PhantomData<E>,
+}
+
+impl<'de, E> Deserializer<'de> for MissingFieldDeserializer<E>
+where
+    E: Error,
+{
+    type Error = E;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::missing_field(self.name))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> Deserialize<'de> for Dog {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        enum Field {
+            Name,
+            Age,
+            Breed,
+            Nickname,
+            Temperament,
+            Ignore,
+        }
+
+        struct FieldVisitor;
+
+        impl Visitor<'_> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                Formatter::write_str(formatter, "field identifier")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                match value {
+                    0u64 => Ok(Field::Name),
+                    1u64 => Ok(Field::Age),
+                    2u64 => Ok(Field::Breed),
+                    3u64 => Ok(Field::Nickname),
+                    4u64 => Ok(Field::Temperament),
+                    _ => Ok(Field::Ignore),
+                }
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                match value {
+                    "name" => Ok(Field::Name),
+                    "age" => Ok(Field::Age),
+                    "breed" => Ok(Field::Breed),
+                    "nickname" => Ok(Field::Nickname),
+                    "temperament" => Ok(Field::Temperament),
+                    _ => Ok(Field::Ignore),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Field {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Deserializer::deserialize_identifier(deserializer, FieldVisitor)
+            }
+        }
+
+        struct DogVisitor<'de> {
+            marker: PhantomData<Dog>,
+            lifetime: PhantomData<&'de ()>,
+        }
+
+        impl<'de> Visitor<'de> for DogVisitor<'de> {
+            type Value = Dog;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                Formatter::write_str(formatter, "struct Dog")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let name = match SeqAccess::next_element::<String>(&mut seq)? {
+                    Some(value) => value,
+                    None => {
+                        return Err(Error::invalid_length(
+                            0usize,
+                            &"struct Dog with at least 2 elements",
+                        ));
+                    }
+                };
+                let age = match SeqAccess::next_element::<u8>(&mut seq)? {
+                    Some(value) => value,
+                    None => {
+                        return Err(Error::invalid_length(
+                            1usize,
+                            &"struct Dog with at least 2 elements",
+                        ));
+                    }
+                };
+                let breed =
+                    SeqAccess::next_element::<Option<Breed>>(&mut seq)?.unwrap_or_default();
+                let nickname = SeqAccess::next_element::<String>(&mut seq)?.unwrap_or_default();
+                let temperament = match SeqAccess::next_element::<String>(&mut seq)? {
+                    Some(value) => value,
+                    None => default_temperament(),
+                };
+                Ok(Dog {
+                    name,
+                    age,
+                    breed,
+                    nickname,
+                    temperament,
+                })
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut name: Option<String> = None;
+                let mut age: Option<u8> = None;
+                let mut breed: Option<Option<Breed>> = None;
+                let mut nickname: Option<String> = None;
+                let mut temperament: Option<String> = None;
+
+                while let Some(key) = MapAccess::next_key::<Field>(&mut map)? {
+                    match key {
+                        Field::Name => {
+                            if Option::is_some(&name) {
+                                return Err(<A::Error as Error>::duplicate_field("name"));
+                            }
+                            name = Some(MapAccess::next_value::<String>(&mut map)?);
+                        }
+                        Field::Age => {
+                            if Option::is_some(&age) {
+                                return Err(<A::Error as Error>::duplicate_field("age"));
+                            }
+                            age = Some(MapAccess::next_value::<u8>(&mut map)?);
+                        }
+                        Field::Breed => {
+                            if Option::is_some(&breed) {
+                                return Err(<A::Error as Error>::duplicate_field("breed"));
+                            }
+                            breed = Some(MapAccess::next_value::<Option<Breed>>(&mut map)?);
+                        }
+                        Field::Nickname => {
+                            if Option::is_some(&nickname) {
+                                return Err(<A::Error as Error>::duplicate_field("nickname"));
+                            }
+                            nickname = Some(MapAccess::next_value::<String>(&mut map)?);
+                        }
+                        Field::Temperament => {
+                            if Option::is_some(&temperament) {
+                                return Err(<A::Error as Error>::duplicate_field("temperament"));
+                            }
+                            temperament = Some(MapAccess::next_value::<String>(&mut map)?);
+                        }
+                        Field::Ignore => {
+                            let _ = MapAccess::next_value::<IgnoredAny>(&mut map)?;
+                        }
+                    }
+                }
+
+                let name = match name {
+                    Some(name) => name,
+                    None => serde::__private::de::missing_field("name")?,
+                };
+                let age = match age {
+                    Some(age) => age,
+                    None => serde::__private::de::missing_field("age")?,
+                };
+                let breed = match breed {
+                    Some(breed) => breed,
+                    None => Option::<Breed>::deserialize(MissingFieldDeserializer {
+                        name: "breed",
+                        marker: PhantomData,
+                    })?,
+                };
+                let nickname = nickname.unwrap_or_default();
+                let temperament = match temperament {
+                    Some(temperament) => temperament,
+                    None => default_temperament(),
+                };
+
+                Ok(Dog {
+                    name,
+                    age,
+                    breed,
+                    nickname,
+                    temperament,
+                })
+            }
+        }
+
+        const FIELDS: &[&str] = &["name", "age", "breed", "nickname", "temperament"];
+
+        Deserializer::deserialize_struct(
+            deserializer,
+            "Dog",
+            FIELDS,
+            DogVisitor {
+                marker: PhantomData,
+                lifetime: PhantomData,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_optional_and_defaulted_fields_are_filled_in() {
+        let dog: Dog = serde_json::from_str(r#"{ "name": "Rocko", "age": 4 }"#).unwrap();
+
+        assert!(dog.breed.is_none());
+        assert_eq!(dog.nickname, "");
+        assert_eq!(dog.temperament, "Friendly");
+    }
+
+    #[test]
+    fn present_fields_override_the_defaults() {
+        let dog: Dog = serde_json::from_str(
+            r#"{ "name": "Rocko", "age": 4, "nickname": "Rock", "temperament": "Grumpy" }"#,
+        )
+        .unwrap();
+
+        assert_eq!(dog.nickname, "Rock");
+        assert_eq!(dog.temperament, "Grumpy");
+    }
+}
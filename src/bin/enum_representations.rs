@@ -0,0 +1,565 @@
+use core::fmt::{self, Formatter};
+use std::marker::PhantomData;
+
+use serde::{
+    de::{Error, MapAccess, SeqAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub fn main() {
+    let internal = r#"{"type": "Vaccination", "name": "Rabies"}"#;
+    let deserialized: InternalTreatment = serde_json::from_str(internal).unwrap();
+    println!(
+        "Internally tagged = {deserialized:?} -> {}",
+        serde_json::to_string(&deserialized).unwrap()
+    );
+
+    let adjacent = r#"{"t": "Surgery", "c": {"procedure": "Neuter", "success": true}}"#;
+    let deserialized: AdjacentTreatment = serde_json::from_str(adjacent).unwrap();
+    println!(
+        "Adjacently tagged = {deserialized:?} -> {}",
+        serde_json::to_string(&deserialized).unwrap()
+    );
+
+    let untagged = r#"{"name": "Rabies"}"#;
+    let deserialized: UntaggedTreatment = serde_json::from_str(untagged).unwrap();
+    println!(
+        "Untagged = {deserialized:?} -> {}",
+        serde_json::to_string(&deserialized).unwrap()
+    );
+}
+
+/// A deserialized value buffered in memory, independent of any wire format.
+///
+/// This is the machinery derive generates behind the scenes for
+/// `#[serde(tag = "...")]`, `#[serde(tag = "...", content = "...")]` and
+/// `#[serde(untagged)]`: the input has to be read once into a neutral
+/// representation before the tag can be inspected, and then replayed into
+/// the real `Visitor` for whichever variant it turns out to be.
+#[derive(Clone, Debug)]
+enum Content {
+    Bool(bool),
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    String(String),
+    Unit,
+    Seq(Vec<Content>),
+    Map(Vec<(Content, Content)>),
+}
+
+struct ContentVisitor;
+
+impl<'de> Visitor<'de> for ContentVisitor {
+    type Value = Content;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        Formatter::write_str(formatter, "any value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Content::Bool(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Content::U64(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Content::I64(value))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Content::F64(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Content::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Content::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Content::Unit)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut elements = Vec::new();
+        while let Some(element) = SeqAccess::next_element(&mut seq)? {
+            elements.push(element);
+        }
+        Ok(Content::Seq(elements))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::new();
+        while let Some(key) = MapAccess::next_key::<Content>(&mut map)? {
+            let value = MapAccess::next_value::<Content>(&mut map)?;
+            entries.push((key, value));
+        }
+        Ok(Content::Map(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for Content {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserializer::deserialize_any(deserializer, ContentVisitor)
+    }
+}
+
+/// Replays a buffered [`Content`] back into a real `Deserialize` impl, the
+/// way derive's internal `ContentDeserializer` lets the variant body be
+/// deserialized a second time from data that has already been consumed once.
+struct ContentDeserializer<E> {
+    content: Content,
+    marker: PhantomData<E>,
+}
+
+impl<E> ContentDeserializer<E> {
+    fn new(content: Content) -> Self {
+        ContentDeserializer {
+            content,
+            marker: PhantomData,
+        }
+    }
+}
+
+struct ContentSeqAccess<E> {
+    iter: std::vec::IntoIter<Content>,
+    marker: PhantomData<E>,
+}
+
+impl<'de, E> SeqAccess<'de> for ContentSeqAccess<E>
+where
+    E: Error,
+{
+    type Error = E;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(content) => seed
+                .deserialize(ContentDeserializer::<E>::new(content))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct ContentMapAccess<E> {
+    iter: std::vec::IntoIter<(Content, Content)>,
+    value: Option<Content>,
+    marker: PhantomData<E>,
+}
+
+impl<'de, E> MapAccess<'de> for ContentMapAccess<E>
+where
+    E: Error,
+{
+    type Error = E;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(ContentDeserializer::<E>::new(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let value = self.value.take().expect("next_value called before next_key");
+        seed.deserialize(ContentDeserializer::<E>::new(value))
+    }
+}
+
+impl<'de, E> Deserializer<'de> for ContentDeserializer<E>
+where
+    E: Error,
+{
+    type Error = E;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Bool(value) => visitor.visit_bool(value),
+            Content::U64(value) => visitor.visit_u64(value),
+            Content::I64(value) => visitor.visit_i64(value),
+            Content::F64(value) => visitor.visit_f64(value),
+            Content::String(value) => visitor.visit_string(value),
+            Content::Unit => visitor.visit_unit(),
+            Content::Seq(elements) => visitor.visit_seq(ContentSeqAccess {
+                iter: elements.into_iter(),
+                marker: PhantomData,
+            }),
+            Content::Map(entries) => visitor.visit_map(ContentMapAccess {
+                iter: entries.into_iter(),
+                value: None,
+                marker: PhantomData,
+            }),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+fn content_field(entries: &[(Content, Content)], name: &str) -> Option<Content> {
+    entries.iter().find_map(|(key, value)| match key {
+        Content::String(key) if key == name => Some(value.clone()),
+        _ => None,
+    })
+}
+
+/// Expansion of `#[derive(Deserialize)] #[serde(tag = "type")] enum InternalTreatment`.
+#[derive(Debug, PartialEq)]
+#[allow(dead_code)]
+enum InternalTreatment {
+    Vaccination { name: String },
+    Surgery { procedure: String, success: bool },
+    Checkup,
+}
+
+impl Serialize for InternalTreatment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            InternalTreatment::Vaccination { ref name } => {
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "InternalTreatment", 2)?;
+                SerializeStruct::serialize_field(&mut serde_state, "type", "Vaccination")?;
+                SerializeStruct::serialize_field(&mut serde_state, "name", name)?;
+                SerializeStruct::end(serde_state)
+            }
+            InternalTreatment::Surgery {
+                ref procedure,
+                ref success,
+            } => {
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "InternalTreatment", 3)?;
+                SerializeStruct::serialize_field(&mut serde_state, "type", "Surgery")?;
+                SerializeStruct::serialize_field(&mut serde_state, "procedure", procedure)?;
+                SerializeStruct::serialize_field(&mut serde_state, "success", success)?;
+                SerializeStruct::end(serde_state)
+            }
+            InternalTreatment::Checkup => {
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "InternalTreatment", 1)?;
+                SerializeStruct::serialize_field(&mut serde_state, "type", "Checkup")?;
+                SerializeStruct::end(serde_state)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for InternalTreatment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let content = Content::deserialize(deserializer)?;
+        let entries = match content {
+            Content::Map(entries) => entries,
+            _ => {
+                return Err(Error::custom(
+                    "expected internally tagged enum InternalTreatment",
+                ))
+            }
+        };
+
+        let tag = match content_field(&entries, "type") {
+            Some(Content::String(tag)) => tag,
+            Some(_) => return Err(Error::custom("tag `type` must be a string")),
+            None => return Err(Error::missing_field("type")),
+        };
+
+        let body = ContentDeserializer::<D::Error>::new(Content::Map(entries));
+
+        match tag.as_str() {
+            "Vaccination" => {
+                #[derive(Deserialize)]
+                struct Body {
+                    name: String,
+                }
+                let body = Body::deserialize(body)?;
+                Ok(InternalTreatment::Vaccination { name: body.name })
+            }
+            "Surgery" => {
+                #[derive(Deserialize)]
+                struct Body {
+                    procedure: String,
+                    success: bool,
+                }
+                let body = Body::deserialize(body)?;
+                Ok(InternalTreatment::Surgery {
+                    procedure: body.procedure,
+                    success: body.success,
+                })
+            }
+            "Checkup" => Ok(InternalTreatment::Checkup),
+            _ => Err(Error::unknown_variant(
+                &tag,
+                &["Vaccination", "Surgery", "Checkup"],
+            )),
+        }
+    }
+}
+
+/// Expansion of `#[derive(Deserialize)] #[serde(tag = "t", content = "c")] enum AdjacentTreatment`.
+#[derive(Debug, PartialEq)]
+#[allow(dead_code)]
+enum AdjacentTreatment {
+    Vaccination { name: String },
+    Surgery { procedure: String, success: bool },
+    Checkup,
+}
+
+impl Serialize for AdjacentTreatment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            AdjacentTreatment::Vaccination { ref name } => {
+                #[derive(Serialize)]
+                struct Body<'a> {
+                    name: &'a String,
+                }
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "AdjacentTreatment", 2)?;
+                SerializeStruct::serialize_field(&mut serde_state, "t", "Vaccination")?;
+                SerializeStruct::serialize_field(&mut serde_state, "c", &Body { name })?;
+                SerializeStruct::end(serde_state)
+            }
+            AdjacentTreatment::Surgery {
+                ref procedure,
+                ref success,
+            } => {
+                #[derive(Serialize)]
+                struct Body<'a> {
+                    procedure: &'a String,
+                    success: &'a bool,
+                }
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "AdjacentTreatment", 2)?;
+                SerializeStruct::serialize_field(&mut serde_state, "t", "Surgery")?;
+                SerializeStruct::serialize_field(
+                    &mut serde_state,
+                    "c",
+                    &Body { procedure, success },
+                )?;
+                SerializeStruct::end(serde_state)
+            }
+            AdjacentTreatment::Checkup => {
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "AdjacentTreatment", 1)?;
+                SerializeStruct::serialize_field(&mut serde_state, "t", "Checkup")?;
+                SerializeStruct::end(serde_state)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AdjacentTreatment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let content = Content::deserialize(deserializer)?;
+        let entries = match content {
+            Content::Map(entries) => entries,
+            _ => {
+                return Err(Error::custom(
+                    "expected adjacently tagged enum AdjacentTreatment",
+                ))
+            }
+        };
+
+        let tag = match content_field(&entries, "t") {
+            Some(Content::String(tag)) => tag,
+            Some(_) => return Err(Error::custom("tag `t` must be a string")),
+            None => return Err(Error::missing_field("t")),
+        };
+        let content = content_field(&entries, "c").unwrap_or(Content::Unit);
+        let body = ContentDeserializer::<D::Error>::new(content);
+
+        match tag.as_str() {
+            "Vaccination" => {
+                #[derive(Deserialize)]
+                struct Body {
+                    name: String,
+                }
+                let body = Body::deserialize(body)?;
+                Ok(AdjacentTreatment::Vaccination { name: body.name })
+            }
+            "Surgery" => {
+                #[derive(Deserialize)]
+                struct Body {
+                    procedure: String,
+                    success: bool,
+                }
+                let body = Body::deserialize(body)?;
+                Ok(AdjacentTreatment::Surgery {
+                    procedure: body.procedure,
+                    success: body.success,
+                })
+            }
+            "Checkup" => Ok(AdjacentTreatment::Checkup),
+            _ => Err(Error::unknown_variant(
+                &tag,
+                &["Vaccination", "Surgery", "Checkup"],
+            )),
+        }
+    }
+}
+
+/// Expansion of `#[derive(Deserialize)] #[serde(untagged)] enum UntaggedTreatment`.
+#[derive(Debug, PartialEq)]
+#[allow(dead_code)]
+enum UntaggedTreatment {
+    Vaccination { name: String },
+    Surgery { procedure: String, success: bool },
+    Checkup,
+}
+
+impl Serialize for UntaggedTreatment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            UntaggedTreatment::Vaccination { ref name } => {
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "UntaggedTreatment", 1)?;
+                SerializeStruct::serialize_field(&mut serde_state, "name", name)?;
+                SerializeStruct::end(serde_state)
+            }
+            UntaggedTreatment::Surgery {
+                ref procedure,
+                ref success,
+            } => {
+                let mut serde_state =
+                    Serializer::serialize_struct(serializer, "UntaggedTreatment", 2)?;
+                SerializeStruct::serialize_field(&mut serde_state, "procedure", procedure)?;
+                SerializeStruct::serialize_field(&mut serde_state, "success", success)?;
+                SerializeStruct::end(serde_state)
+            }
+            UntaggedTreatment::Checkup => {
+                let serde_state =
+                    Serializer::serialize_struct(serializer, "UntaggedTreatment", 0)?;
+                SerializeStruct::end(serde_state)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for UntaggedTreatment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let content = Content::deserialize(deserializer)?;
+
+        #[derive(Deserialize)]
+        struct Vaccination {
+            name: String,
+        }
+        if let Ok(body) = Vaccination::deserialize(ContentDeserializer::<D::Error>::new(
+            content.clone(),
+        )) {
+            return Ok(UntaggedTreatment::Vaccination { name: body.name });
+        }
+
+        #[derive(Deserialize)]
+        struct Surgery {
+            procedure: String,
+            success: bool,
+        }
+        if let Ok(body) = Surgery::deserialize(ContentDeserializer::<D::Error>::new(
+            content.clone(),
+        )) {
+            return Ok(UntaggedTreatment::Surgery {
+                procedure: body.procedure,
+                success: body.success,
+            });
+        }
+
+        if matches!(content, Content::Map(ref entries) if entries.is_empty()) {
+            return Ok(UntaggedTreatment::Checkup);
+        }
+
+        Err(Error::custom(
+            "data did not match any variant of untagged enum UntaggedTreatment",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internally_tagged_round_trip() {
+        let treatment: InternalTreatment =
+            serde_json::from_str(r#"{"type": "Surgery", "procedure": "Neuter", "success": true}"#)
+                .unwrap();
+
+        let serialized = serde_json::to_string(&treatment).unwrap();
+        let round_tripped: InternalTreatment = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(treatment, round_tripped);
+    }
+
+    #[test]
+    fn adjacently_tagged_round_trip() {
+        let treatment: AdjacentTreatment =
+            serde_json::from_str(r#"{"t": "Vaccination", "c": {"name": "Rabies"}}"#).unwrap();
+
+        let serialized = serde_json::to_string(&treatment).unwrap();
+        let round_tripped: AdjacentTreatment = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(treatment, round_tripped);
+    }
+
+    #[test]
+    fn untagged_round_trip() {
+        let treatment: UntaggedTreatment = serde_json::from_str(r#"{}"#).unwrap();
+
+        let serialized = serde_json::to_string(&treatment).unwrap();
+        let round_tripped: UntaggedTreatment = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(treatment, round_tripped);
+    }
+}
@@ -0,0 +1,343 @@
+use core::fmt::{self, Formatter};
+use std::marker::PhantomData;
+
+use serde::{
+    de::{EnumAccess, Error, IgnoredAny, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor},
+    ser::{SerializeStructVariant, SerializeTupleVariant},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub fn main() {
+    let breeds = [
+        Breed::Mixed("Labrador".to_owned()),
+        Breed::Crossbreed("Labrador".to_owned(), "Poodle".to_owned()),
+        Breed::Custom {
+            origin: "Siberia".to_owned(),
+            size: 22,
+        },
+    ];
+
+    for breed in &breeds {
+        let serialized = serde_json::to_string(breed).unwrap();
+        println!("Serialized breed = {serialized}");
+
+        let deserialized: Breed = serde_json::from_str(&serialized).unwrap();
+        let round_tripped = serde_json::to_string(&deserialized).unwrap();
+
+        assert_eq!(serialized, round_tripped);
+    }
+}
+
+enum Breed {
+    Mixed(String),
+    Crossbreed(String, String),
+    Custom { origin: String, size: u8 },
+}
+
+impl Serialize for Breed {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Breed::Mixed(ref field0) => {
+                Serializer::serialize_newtype_variant(serializer, "Breed", 0u32, "Mixed", field0)
+            }
+            Breed::Crossbreed(ref field0, ref field1) => {
+                let mut serde_state = Serializer::serialize_tuple_variant(
+                    serializer,
+                    "Breed",
+                    1u32,
+                    "Crossbreed",
+                    false as usize + 1 + 1,
+                )?;
+                SerializeTupleVariant::serialize_field(&mut serde_state, field0)?;
+                SerializeTupleVariant::serialize_field(&mut serde_state, field1)?;
+                SerializeTupleVariant::end(serde_state)
+            }
+            Breed::Custom {
+                ref origin,
+                ref size,
+            } => {
+                let mut serde_state = Serializer::serialize_struct_variant(
+                    serializer,
+                    "Breed",
+                    2u32,
+                    "Custom",
+                    false as usize + 1 + 1,
+                )?;
+                SerializeStructVariant::serialize_field(&mut serde_state, "origin", origin)?;
+                SerializeStructVariant::serialize_field(&mut serde_state, "size", size)?;
+                SerializeStructVariant::end(serde_state)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Breed {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        enum Field {
+            Mixed,
+            Crossbreed,
+            Custom,
+        }
+
+        struct FieldVisitor;
+
+        impl Visitor<'_> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                Formatter::write_str(formatter, "variant identifier")
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                match value {
+                    0u64 => Ok(Field::Mixed),
+                    1u64 => Ok(Field::Crossbreed),
+                    2u64 => Ok(Field::Custom),
+                    _ => Err(Error::invalid_value(
+                        Unexpected::Unsigned(value),
+                        &"variant index 0 <= i < 3",
+                    )),
+                }
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                match value {
+                    "Mixed" => Ok(Field::Mixed),
+                    "Crossbreed" => Ok(Field::Crossbreed),
+                    "Custom" => Ok(Field::Custom),
+                    _ => Err(Error::unknown_variant(value, VARIANTS)),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Field {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Deserializer::deserialize_identifier(deserializer, FieldVisitor)
+            }
+        }
+
+        struct BreedVisitor<'de> {
+            marker: PhantomData<Breed>,
+            lifetime: PhantomData<&'de ()>,
+        }
+
+        impl<'de> Visitor<'de> for BreedVisitor<'de> {
+            type Value = Breed;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                Formatter::write_str(formatter, "enum Breed")
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                match EnumAccess::variant(data)? {
+                    (Field::Mixed, variant) => {
+                        VariantAccess::newtype_variant::<String>(variant).map(Breed::Mixed)
+                    }
+                    (Field::Crossbreed, variant) => {
+                        struct CrossbreedVisitor;
+
+                        impl<'de> Visitor<'de> for CrossbreedVisitor {
+                            type Value = (String, String);
+
+                            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                                Formatter::write_str(formatter, "tuple variant Breed::Crossbreed")
+                            }
+
+                            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                            where
+                                A: SeqAccess<'de>,
+                            {
+                                let field0 = match SeqAccess::next_element::<String>(&mut seq)? {
+                                    Some(value) => value,
+                                    None => {
+                                        return Err(Error::invalid_length(
+                                            0usize,
+                                            &"tuple variant Breed::Crossbreed with 2 elements",
+                                        ));
+                                    }
+                                };
+                                let field1 = match SeqAccess::next_element::<String>(&mut seq)? {
+                                    Some(value) => value,
+                                    None => {
+                                        return Err(Error::invalid_length(
+                                            1usize,
+                                            &"tuple variant Breed::Crossbreed with 2 elements",
+                                        ));
+                                    }
+                                };
+                                Ok((field0, field1))
+                            }
+                        }
+
+                        VariantAccess::tuple_variant(variant, 2, CrossbreedVisitor)
+                            .map(|(field0, field1)| Breed::Crossbreed(field0, field1))
+                    }
+                    (Field::Custom, variant) => {
+                        enum CustomField {
+                            Origin,
+                            Size,
+                            Ignore,
+                        }
+
+                        struct CustomFieldVisitor;
+
+                        impl Visitor<'_> for CustomFieldVisitor {
+                            type Value = CustomField;
+
+                            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                                Formatter::write_str(formatter, "field identifier")
+                            }
+
+                            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+                            where
+                                E: Error,
+                            {
+                                match value {
+                                    0u64 => Ok(CustomField::Origin),
+                                    1u64 => Ok(CustomField::Size),
+                                    _ => Ok(CustomField::Ignore),
+                                }
+                            }
+
+                            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+                            where
+                                E: Error,
+                            {
+                                match value {
+                                    "origin" => Ok(CustomField::Origin),
+                                    "size" => Ok(CustomField::Size),
+                                    _ => Ok(CustomField::Ignore),
+                                }
+                            }
+                        }
+
+                        impl<'de> Deserialize<'de> for CustomField {
+                            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                            where
+                                D: Deserializer<'de>,
+                            {
+                                Deserializer::deserialize_identifier(
+                                    deserializer,
+                                    CustomFieldVisitor,
+                                )
+                            }
+                        }
+
+                        struct CustomVisitor;
+
+                        impl<'de> Visitor<'de> for CustomVisitor {
+                            type Value = Breed;
+
+                            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                                Formatter::write_str(formatter, "struct variant Breed::Custom")
+                            }
+
+                            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                            where
+                                A: SeqAccess<'de>,
+                            {
+                                let origin = match SeqAccess::next_element::<String>(&mut seq)? {
+                                    Some(value) => value,
+                                    None => {
+                                        return Err(Error::invalid_length(
+                                            0usize,
+                                            &"struct variant Breed::Custom with 2 elements",
+                                        ));
+                                    }
+                                };
+                                let size = match SeqAccess::next_element::<u8>(&mut seq)? {
+                                    Some(value) => value,
+                                    None => {
+                                        return Err(Error::invalid_length(
+                                            1usize,
+                                            &"struct variant Breed::Custom with 2 elements",
+                                        ));
+                                    }
+                                };
+                                Ok(Breed::Custom { origin, size })
+                            }
+
+                            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                            where
+                                A: MapAccess<'de>,
+                            {
+                                let mut origin: Option<String> = None;
+                                let mut size: Option<u8> = None;
+                                while let Some(key) = MapAccess::next_key::<CustomField>(&mut map)?
+                                {
+                                    match key {
+                                        CustomField::Origin => {
+                                            if Option::is_some(&origin) {
+                                                return Err(<A::Error as Error>::duplicate_field(
+                                                    "origin",
+                                                ));
+                                            }
+                                            origin = Some(MapAccess::next_value::<String>(
+                                                &mut map,
+                                            )?);
+                                        }
+                                        CustomField::Size => {
+                                            if Option::is_some(&size) {
+                                                return Err(<A::Error as Error>::duplicate_field(
+                                                    "size",
+                                                ));
+                                            }
+                                            size = Some(MapAccess::next_value::<u8>(&mut map)?);
+                                        }
+                                        CustomField::Ignore => {
+                                            let _ =
+                                                MapAccess::next_value::<IgnoredAny>(&mut map)?;
+                                        }
+                                    }
+                                }
+                                let origin = match origin {
+                                    Some(origin) => origin,
+                                    None => serde::__private::de::missing_field("origin")?,
+                                };
+                                let size = match size {
+                                    Some(size) => size,
+                                    None => serde::__private::de::missing_field("size")?,
+                                };
+                                Ok(Breed::Custom { origin, size })
+                            }
+                        }
+
+                        const CUSTOM_FIELDS: &[&str] = &["origin", "size"];
+
+                        VariantAccess::struct_variant(variant, CUSTOM_FIELDS, CustomVisitor)
+                    }
+                }
+            }
+        }
+
+        const VARIANTS: &[&str] = &["Mixed", "Crossbreed", "Custom"];
+
+        Deserializer::deserialize_enum(
+            deserializer,
+            "Breed",
+            VARIANTS,
+            BreedVisitor {
+                marker: PhantomData,
+                lifetime: PhantomData,
+            },
+        )
+    }
+}
@@ -0,0 +1,337 @@
+use core::fmt::{self, Formatter};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use serde::de::{DeserializeSeed, Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn main() {
+    let shared = Rc::new(Node {
+        name: "shared".to_owned(),
+        children: RefCell::new(Vec::new()),
+    });
+    let left = Rc::new(Node {
+        name: "left".to_owned(),
+        children: RefCell::new(vec![Rc::clone(&shared)]),
+    });
+    let right = Rc::new(Node {
+        name: "right".to_owned(),
+        children: RefCell::new(vec![Rc::clone(&shared)]),
+    });
+    let root = Rc::new(Node {
+        name: "root".to_owned(),
+        children: RefCell::new(vec![left, right]),
+    });
+
+    let serialized = serde_json::to_string_pretty(&Graph(root)).unwrap();
+    println!("Serialized graph = {serialized}");
+
+    let deserialized: Graph = serde_json::from_str(&serialized).unwrap();
+    println!("Deserialized root = {}", deserialized.0.name);
+}
+
+/// A node in a graph that is a tree everywhere except where `Rc::clone`
+/// makes two parents point at the same child.
+struct Node {
+    name: String,
+    children: RefCell<Vec<Rc<Node>>>,
+}
+
+/// The root of a shared graph, with manual `Serialize`/`Deserialize` so the
+/// id-assignment and lookup logic driving deduplication stays visible
+/// instead of hiding behind `#[derive]`.
+struct Graph(Rc<Node>);
+
+/// Wraps a node together with the id table being built up as the graph is
+/// walked, so every recursive call sees the same `seen` set.
+struct NodeRef<'a> {
+    node: &'a Rc<Node>,
+    seen: &'a RefCell<HashMap<*const Node, usize>>,
+}
+
+impl Serialize for Graph {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let seen = RefCell::new(HashMap::new());
+        NodeRef {
+            node: &self.0,
+            seen: &seen,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Serialize for NodeRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let ptr = Rc::as_ptr(self.node);
+
+        let (id, first_time) = {
+            let mut seen = self.seen.borrow_mut();
+            match seen.get(&ptr) {
+                Some(&id) => (id, false),
+                None => {
+                    let id = seen.len();
+                    seen.insert(ptr, id);
+                    (id, true)
+                }
+            }
+        };
+
+        if !first_time {
+            let mut serde_state = serializer.serialize_map(Some(1))?;
+            SerializeMap::serialize_entry(&mut serde_state, "ref", &id)?;
+            return SerializeMap::end(serde_state);
+        }
+
+        let guard = self.node.children.borrow();
+        let children: Vec<NodeRef> = guard
+            .iter()
+            .map(|child| NodeRef {
+                node: child,
+                seen: self.seen,
+            })
+            .collect();
+
+        let mut serde_state = serializer.serialize_map(Some(3))?;
+        SerializeMap::serialize_entry(&mut serde_state, "id", &id)?;
+        SerializeMap::serialize_entry(&mut serde_state, "name", &self.node.name)?;
+        SerializeMap::serialize_entry(&mut serde_state, "children", &children)?;
+        SerializeMap::end(serde_state)
+    }
+}
+
+impl<'de> Deserialize<'de> for Graph {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let nodes = RefCell::new(HashMap::new());
+        NodeSeed { nodes: &nodes }
+            .deserialize(deserializer)
+            .map(Graph)
+    }
+}
+
+/// Resolves one node (or `{"ref": id}` back-reference) against the shared
+/// `nodes` table, so previously constructed `Rc<Node>`s are reused rather
+/// than rebuilt.
+struct NodeSeed<'a> {
+    nodes: &'a RefCell<HashMap<usize, Rc<Node>>>,
+}
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_> {
+    type Value = Rc<Node>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserializer::deserialize_map(deserializer, NodeVisitor { nodes: self.nodes })
+    }
+}
+
+struct NodeVisitor<'a> {
+    nodes: &'a RefCell<HashMap<usize, Rc<Node>>>,
+}
+
+impl<'de> Visitor<'de> for NodeVisitor<'_> {
+    type Value = Rc<Node>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        Formatter::write_str(formatter, "a graph node or a {\"ref\": id} back-reference")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        enum Field {
+            Id,
+            Name,
+            Children,
+            Ref,
+            Ignore,
+        }
+
+        struct FieldVisitor;
+
+        impl Visitor<'_> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                Formatter::write_str(formatter, "field identifier")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                match value {
+                    "id" => Ok(Field::Id),
+                    "name" => Ok(Field::Name),
+                    "children" => Ok(Field::Children),
+                    "ref" => Ok(Field::Ref),
+                    _ => Ok(Field::Ignore),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Field {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Deserializer::deserialize_identifier(deserializer, FieldVisitor)
+            }
+        }
+
+        let mut id: Option<usize> = None;
+        let mut name: Option<String> = None;
+        let mut reference: Option<usize> = None;
+        let mut node: Option<Rc<Node>> = None;
+
+        while let Some(key) = MapAccess::next_key::<Field>(&mut map)? {
+            match key {
+                Field::Id => {
+                    id = Some(MapAccess::next_value(&mut map)?);
+                }
+                Field::Name => {
+                    name = Some(MapAccess::next_value(&mut map)?);
+                }
+                Field::Ref => {
+                    reference = Some(MapAccess::next_value(&mut map)?);
+                }
+                Field::Children => {
+                    let id = id.ok_or_else(|| Error::missing_field("id"))?;
+                    let name = name.clone().ok_or_else(|| Error::missing_field("name"))?;
+
+                    // Insert the node before deserializing its children, so
+                    // that a child referencing this id back resolves to the
+                    // same `Rc` instead of recursing forever.
+                    let existing = self.nodes.borrow().get(&id).cloned();
+                    let created = match existing {
+                        Some(created) => created,
+                        None => {
+                            let created = Rc::new(Node {
+                                name,
+                                children: RefCell::new(Vec::new()),
+                            });
+                            self.nodes.borrow_mut().insert(id, Rc::clone(&created));
+                            created
+                        }
+                    };
+
+                    let children =
+                        MapAccess::next_value_seed(&mut map, ChildrenSeed { nodes: self.nodes })?;
+                    *created.children.borrow_mut() = children;
+                    node = Some(created);
+                }
+                Field::Ignore => {
+                    let _ = MapAccess::next_value::<IgnoredAny>(&mut map)?;
+                }
+            }
+        }
+
+        if let Some(reference) = reference {
+            return self.nodes.borrow().get(&reference).cloned().ok_or_else(|| {
+                Error::custom(format_args!("reference to unknown node id {reference}"))
+            });
+        }
+
+        if let Some(node) = node {
+            return Ok(node);
+        }
+
+        let id = id.ok_or_else(|| Error::missing_field("id"))?;
+        let name = name.ok_or_else(|| Error::missing_field("name"))?;
+
+        let node = Rc::new(Node {
+            name,
+            children: RefCell::new(Vec::new()),
+        });
+        self.nodes.borrow_mut().insert(id, Rc::clone(&node));
+        Ok(node)
+    }
+}
+
+struct ChildrenSeed<'a> {
+    nodes: &'a RefCell<HashMap<usize, Rc<Node>>>,
+}
+
+impl<'de> DeserializeSeed<'de> for ChildrenSeed<'_> {
+    type Value = Vec<Rc<Node>>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ChildrenVisitor<'a> {
+            nodes: &'a RefCell<HashMap<usize, Rc<Node>>>,
+        }
+
+        impl<'de> Visitor<'de> for ChildrenVisitor<'_> {
+            type Value = Vec<Rc<Node>>;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                Formatter::write_str(formatter, "a sequence of graph nodes")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut children = Vec::new();
+                while let Some(child) =
+                    SeqAccess::next_element_seed(&mut seq, NodeSeed { nodes: self.nodes })?
+                {
+                    children.push(child);
+                }
+                Ok(children)
+            }
+        }
+
+        Deserializer::deserialize_seq(deserializer, ChildrenVisitor { nodes: self.nodes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_a_diamond_without_duplicating_the_shared_child() {
+        let shared = Rc::new(Node {
+            name: "shared".to_owned(),
+            children: RefCell::new(Vec::new()),
+        });
+        let left = Rc::new(Node {
+            name: "left".to_owned(),
+            children: RefCell::new(vec![Rc::clone(&shared)]),
+        });
+        let right = Rc::new(Node {
+            name: "right".to_owned(),
+            children: RefCell::new(vec![Rc::clone(&shared)]),
+        });
+        let root = Rc::new(Node {
+            name: "root".to_owned(),
+            children: RefCell::new(vec![left, right]),
+        });
+
+        let serialized = serde_json::to_string(&Graph(root)).unwrap();
+        let deserialized: Graph = serde_json::from_str(&serialized).unwrap();
+
+        let root = deserialized.0;
+        let children = root.children.borrow();
+        let left_shared = Rc::clone(&children[0].children.borrow()[0]);
+        let right_shared = Rc::clone(&children[1].children.borrow()[0]);
+
+        assert!(Rc::ptr_eq(&left_shared, &right_shared));
+    }
+}
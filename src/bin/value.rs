@@ -0,0 +1,178 @@
+use core::fmt::{self, Formatter};
+use std::collections::BTreeMap;
+
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub fn main() {
+    let string = r#"
+        {
+            "name": "Rocko",
+            "age": 4,
+            "breed": null,
+            "vaccinated": true,
+            "tags": ["good boy", "loud"]
+        }
+    "#;
+
+    let value: Value = serde_json::from_str(string).unwrap();
+    println!("Deserialized value = {value:?}");
+
+    let serialized = serde_json::to_string_pretty(&value).unwrap();
+    println!("Serialized value = {serialized}");
+}
+
+type Map = BTreeMap<String, Value>;
+
+/// A format-agnostic dynamic value, built purely from the `Visitor` API
+/// rather than `deserialize_struct`'s fixed field list.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Map),
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        Formatter::write_str(formatter, "any valid value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(Value::Number(value as f64))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Value::Number(value as f64))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(Value::Number(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut elements = Vec::new();
+        while let Some(element) = SeqAccess::next_element(&mut seq)? {
+            elements.push(element);
+        }
+        Ok(Value::Array(elements))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Map::new();
+        while let Some(key) = MapAccess::next_key::<String>(&mut map)? {
+            let value = MapAccess::next_value::<Value>(&mut map)?;
+            entries.insert(key, value);
+        }
+        Ok(Value::Object(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserializer::deserialize_any(deserializer, ValueVisitor)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(value) => serializer.serialize_bool(value),
+            Value::Number(value) => serializer.serialize_f64(value),
+            Value::String(ref value) => serializer.serialize_str(value),
+            Value::Array(ref elements) => {
+                let mut serde_state = serializer.serialize_seq(Some(elements.len()))?;
+                for element in elements {
+                    SerializeSeq::serialize_element(&mut serde_state, element)?;
+                }
+                SerializeSeq::end(serde_state)
+            }
+            Value::Object(ref entries) => {
+                let mut serde_state = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    SerializeMap::serialize_entry(&mut serde_state, key, value)?;
+                }
+                SerializeMap::end(serde_state)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_every_shape() {
+        let value: Value =
+            serde_json::from_str(r#"{"a": null, "b": true, "c": 1.5, "d": "x", "e": [1, 2]}"#)
+                .unwrap();
+
+        let Value::Object(map) = value else {
+            panic!("expected an object");
+        };
+        assert_eq!(map["a"], Value::Null);
+        assert_eq!(map["b"], Value::Bool(true));
+        assert_eq!(map["c"], Value::Number(1.5));
+        assert_eq!(map["d"], Value::String("x".to_owned()));
+        assert_eq!(map["e"], Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]));
+    }
+
+    #[test]
+    fn round_trips_through_serde_json() {
+        let original = r#"{"name":"Rocko","tags":["good boy","loud"]}"#;
+        let value: Value = serde_json::from_str(original).unwrap();
+        let reparsed: Value = serde_json::from_str(&serde_json::to_string(&value).unwrap()).unwrap();
+        assert_eq!(value, reparsed);
+    }
+}
@@ -0,0 +1,287 @@
+use core::fmt::{self, Formatter};
+use std::collections::{btree_map, BTreeMap};
+use std::vec;
+
+use serde::de::{DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+
+pub fn main() {
+    let mut fields = Map::new();
+    fields.insert("name".to_owned(), Value::String("Rocko".to_owned()));
+    fields.insert("age".to_owned(), Value::Number(4.0));
+    fields.insert("breed".to_owned(), Value::String("Husky".to_owned()));
+
+    let tree = Value::Object(fields);
+
+    let dog = Dog::deserialize(ValueDeserializer::new(tree)).unwrap();
+
+    println!("Deserialized dog age = {}", dog.age);
+}
+
+type Map = BTreeMap<String, Value>;
+
+/// The owned value tree this example deserializes from, in place of JSON
+/// text. This is the producer side of serde: a complete `Deserializer` that
+/// drives any `Visitor` from an owned tree. The other `Deserializer` impls in
+/// this crate (`ContentDeserializer`, `MissingFieldDeserializer`) are helpers
+/// inside a `Deserialize` impl; the remaining examples only consume a
+/// format's existing `Deserializer`.
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Map),
+}
+
+#[derive(Debug)]
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        Formatter::write_str(formatter, &self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        Error(msg.to_string())
+    }
+}
+
+struct ValueDeserializer {
+    value: Value,
+}
+
+impl ValueDeserializer {
+    fn new(value: Value) -> Self {
+        ValueDeserializer { value }
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(value) => visitor.visit_bool(value),
+            Value::Number(value) if value >= 0.0 && value.fract() == 0.0 => {
+                visitor.visit_u64(value as u64)
+            }
+            Value::Number(value) if value.fract() == 0.0 => visitor.visit_i64(value as i64),
+            Value::Number(value) => visitor.visit_f64(value),
+            Value::String(value) => visitor.visit_string(value),
+            Value::Array(elements) => visitor.visit_seq(ValueSeqAccess {
+                iter: elements.into_iter(),
+            }),
+            Value::Object(entries) => visitor.visit_map(ValueMapAccess {
+                iter: entries.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Value::String(variant) => visitor.visit_enum(ValueEnumAccess {
+                variant,
+                value: None,
+            }),
+            Value::Object(mut entries) if entries.len() == 1 => {
+                let (variant, value) = entries.pop_first().expect("checked len() == 1");
+                visitor.visit_enum(ValueEnumAccess {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            _ => Err(serde::de::Error::custom(
+                "expected string or single-entry map for enum",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct ValueSeqAccess {
+    iter: vec::IntoIter<Value>,
+}
+
+impl<'de> SeqAccess<'de> for ValueSeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(ValueDeserializer::new(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct ValueMapAccess {
+    iter: btree_map::IntoIter<String, Value>,
+    value: Option<Value>,
+}
+
+impl<'de> MapAccess<'de> for ValueMapAccess {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(ValueDeserializer::new(Value::String(key)))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(ValueDeserializer::new(value))
+    }
+}
+
+struct ValueEnumAccess {
+    variant: String,
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for ValueEnumAccess {
+    type Error = Error;
+    type Variant = ValueVariantAccess;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant =
+            seed.deserialize(ValueDeserializer::new(Value::String(self.variant)))?;
+        Ok((variant, ValueVariantAccess { value: self.value }))
+    }
+}
+
+struct ValueVariantAccess {
+    value: Option<Value>,
+}
+
+impl<'de> VariantAccess<'de> for ValueVariantAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.value {
+            None => Ok(()),
+            Some(_) => Err(serde::de::Error::custom("expected unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(ValueDeserializer::new(value)),
+            None => Err(serde::de::Error::custom("expected newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Array(elements)) => visitor.visit_seq(ValueSeqAccess {
+                iter: elements.into_iter(),
+            }),
+            _ => Err(serde::de::Error::custom("expected tuple variant")),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Object(entries)) => visitor.visit_map(ValueMapAccess {
+                iter: entries.into_iter(),
+                value: None,
+            }),
+            _ => Err(serde::de::Error::custom("expected struct variant")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Dog {
+    #[allow(dead_code)]
+    name: String,
+    age: u8,
+    #[allow(dead_code)]
+    breed: Breed,
+}
+
+#[derive(Deserialize)]
+enum Breed {
+    Husky,
+    #[allow(dead_code)]
+    Teckel,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializes_dog_from_value_tree() {
+        let mut fields = Map::new();
+        fields.insert("name".to_owned(), Value::String("Rocko".to_owned()));
+        fields.insert("age".to_owned(), Value::Number(4.0));
+        fields.insert("breed".to_owned(), Value::String("Husky".to_owned()));
+
+        let dog = Dog::deserialize(ValueDeserializer::new(Value::Object(fields))).unwrap();
+
+        assert_eq!(dog.age, 4);
+        assert!(matches!(dog.breed, Breed::Husky));
+    }
+}